@@ -0,0 +1,30 @@
+// Copyright 2020 Nym Technologies SA
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use futures::channel::mpsc;
+use nymsphinx::forwarding::packet::MixPacket;
+
+// the channel is bounded so that a burst of outgoing packets cannot grow without limit while
+// waiting for `MixTrafficController` to forward them - producers are expected to await capacity
+// rather than enqueue unboundedly.
+pub(crate) type BatchMixMessageSender = mpsc::Sender<Vec<MixPacket>>;
+pub(crate) type BatchMixMessageReceiver = mpsc::Receiver<Vec<MixPacket>>;
+
+/// Creates the bounded channel pair used to feed prepared sphinx packets into the
+/// `MixTrafficController`.
+pub(crate) fn new_mix_message_channel(
+    capacity: usize,
+) -> (BatchMixMessageSender, BatchMixMessageReceiver) {
+    mpsc::channel(capacity)
+}
@@ -16,8 +16,9 @@ use crate::client::mix_traffic::BatchMixMessageSender;
 use crate::client::real_messages_control::acknowledgement_control::SentPacketNotificationSender;
 use crate::client::topology_control::TopologyAccessor;
 use futures::channel::mpsc;
+use futures::sink::SinkExt;
 use futures::task::{Context, Poll};
-use futures::{Future, Stream, StreamExt};
+use futures::{Future, Sink, Stream, StreamExt};
 use log::*;
 use nymsphinx::acknowledgements::AckKey;
 use nymsphinx::addressing::clients::Recipient;
@@ -25,13 +26,87 @@ use nymsphinx::chunking::fragment::FragmentIdentifier;
 use nymsphinx::cover::generate_loop_cover_packet;
 use nymsphinx::forwarding::packet::MixPacket;
 use nymsphinx::utils::sample_poisson_duration;
-use rand::{CryptoRng, Rng};
+use rand::{CryptoRng, Rng, RngCore};
 use std::collections::VecDeque;
 use std::pin::Pin;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time;
 
+/// A pluggable source of the delay between sending subsequent packets, letting operators trade
+/// anonymity-set properties against latency/jitter predictability. Takes `&mut dyn RngCore`
+/// rather than being generic over `R: Rng` so the trait stays object-safe and a concrete
+/// distribution can be chosen at runtime from a config value, as `Box<dyn DelayDistribution>`.
+pub(crate) trait DelayDistribution {
+    fn sample(&mut self, rng: &mut dyn RngCore) -> Duration;
+}
+
+/// The original behaviour: an exponentially distributed (Poisson process) inter-packet delay.
+pub(crate) struct PoissonDelay {
+    average_delay: Duration,
+}
+
+impl PoissonDelay {
+    pub(crate) fn new(average_delay: Duration) -> Self {
+        PoissonDelay { average_delay }
+    }
+}
+
+impl DelayDistribution for PoissonDelay {
+    fn sample(&mut self, rng: &mut dyn RngCore) -> Duration {
+        sample_poisson_duration(rng, self.average_delay)
+    }
+}
+
+/// A delay drawn uniformly at random from `[min_delay, max_delay]`, trading the Poisson
+/// distribution's anonymity-set guarantees for bounded, more predictable jitter.
+pub(crate) struct UniformDelay {
+    min_delay: Duration,
+    max_delay: Duration,
+}
+
+impl UniformDelay {
+    pub(crate) fn new(min_delay: Duration, max_delay: Duration) -> Self {
+        assert!(
+            min_delay <= max_delay,
+            "min_delay must not be greater than max_delay"
+        );
+        UniformDelay {
+            min_delay,
+            max_delay,
+        }
+    }
+}
+
+impl DelayDistribution for UniformDelay {
+    fn sample(&mut self, rng: &mut dyn RngCore) -> Duration {
+        let min_nanos = self.min_delay.as_nanos() as u64;
+        let max_nanos = self.max_delay.as_nanos() as u64;
+        if min_nanos == max_nanos {
+            return self.min_delay;
+        }
+        // upper bound inclusive, to actually honour the `[min_delay, max_delay]` contract above
+        Duration::from_nanos(rng.gen_range(min_nanos, max_nanos + 1))
+    }
+}
+
+/// A fixed, constant-rate delay - no randomness at all.
+pub(crate) struct ConstantDelay {
+    delay: Duration,
+}
+
+impl ConstantDelay {
+    pub(crate) fn new(delay: Duration) -> Self {
+        ConstantDelay { delay }
+    }
+}
+
+impl DelayDistribution for ConstantDelay {
+    fn sample(&mut self, _rng: &mut dyn RngCore) -> Duration {
+        self.delay
+    }
+}
+
 /// Configurable parameters of the `OutQueueControl`
 pub(crate) struct Config {
     /// Average delay an acknowledgement packet is going to get delay at a single mixnode.
@@ -40,22 +115,55 @@ pub(crate) struct Config {
     /// Average delay a data packet is going to get delay at a single mixnode.
     average_packet_delay: Duration,
 
-    /// Average delay between sending subsequent packets.
-    average_message_sending_delay: Duration,
+    /// Distribution the delay between sending subsequent packets is drawn from, chosen at
+    /// runtime from a config value.
+    delay_distribution: Box<dyn DelayDistribution + Send>,
+
+    /// Number of batches of real messages that can be queued up on the channel leading into
+    /// this stream before a producer has to wait for space to free up. The bounded channel
+    /// holds one further batch per sender on top of this.
+    channel_capacity: usize,
+
+    /// Maximum amount of time a real message is allowed to sit around waiting to be sent
+    /// before it is considered stale and dropped rather than transmitted.
+    max_message_age: Duration,
+
+    /// Starting wait before retrying a message that was held back due to there being no
+    /// valid network topology available.
+    topology_backoff_base: Duration,
+
+    /// Upper bound the topology retry wait is allowed to grow to.
+    topology_backoff_cap: Duration,
 }
 
 impl Config {
     pub(crate) fn new(
         average_ack_delay: Duration,
         average_packet_delay: Duration,
-        average_message_sending_delay: Duration,
+        delay_distribution: Box<dyn DelayDistribution + Send>,
+        channel_capacity: usize,
+        max_message_age: Duration,
+        topology_backoff_base: Duration,
+        topology_backoff_cap: Duration,
     ) -> Self {
         Config {
             average_ack_delay,
             average_packet_delay,
-            average_message_sending_delay,
+            delay_distribution,
+            channel_capacity,
+            max_message_age,
+            topology_backoff_base,
+            topology_backoff_cap,
         }
     }
+
+    pub(crate) fn channel_capacity(&self) -> usize {
+        self.channel_capacity
+    }
+
+    pub(crate) fn max_message_age(&self) -> Duration {
+        self.max_message_age
+    }
 }
 
 pub(crate) struct OutQueueControl<R>
@@ -71,9 +179,10 @@ where
     /// Channel used for notifying of a real packet being sent out. Used to start up retransmission timer.
     sent_notifier: SentPacketNotificationSender,
 
-    /// Internal state, determined by `average_message_sending_delay`,
-    /// used to keep track of when a next packet should be sent out.
-    next_delay: time::Delay,
+    /// Internal state, determined by `config.delay_distribution`, used to keep track of when
+    /// a next packet should be sent out. `None` when running in VPN mode, in which case real
+    /// messages are yielded as soon as they are available and no cover traffic is injected.
+    next_delay: Option<time::Delay>,
 
     /// Channel used for sending prepared sphinx packets to `MixTrafficController` that sends them
     /// out to the network without any further delays.
@@ -94,26 +203,83 @@ where
 
     /// Buffer containing all real messages received. It is first exhausted before more are pulled.
     received_buffer: VecDeque<RealMessage>,
+
+    /// A message that could not be sent because no valid topology was available, waiting
+    /// to be retried. While this is set, it takes priority over producing any new message.
+    pending_retry: Option<PendingRetry>,
+
+    /// The backoff that produced the message currently being retried, carried from the moment
+    /// `poll_next` hands it back out until `on_message` either sends it or has to back off again.
+    /// `None` means the message currently being processed is not a retry.
+    pending_retry_backoff: Option<Duration>,
+}
+
+/// A message held back after a failed attempt to obtain a valid topology, waiting to be retried.
+struct PendingRetry {
+    message: StreamMessage,
+    delay: time::Delay,
+    backoff: Duration,
+}
+
+/// Computes the wait before the next topology retry: `base` on the first failure, doubling
+/// (up to `cap`) every time the same message fails again in a row.
+fn next_topology_backoff(
+    prior_backoff: Option<Duration>,
+    base: Duration,
+    cap: Duration,
+) -> Duration {
+    prior_backoff
+        .map(|prior| (prior * 2).min(cap))
+        .unwrap_or(base)
 }
 
 pub(crate) struct RealMessage {
     mix_packet: MixPacket,
     fragment_id: FragmentIdentifier,
+
+    /// Point in time after which this fragment is considered stale and must not be sent.
+    deadline: Instant,
 }
 
 impl RealMessage {
-    pub(crate) fn new(mix_packet: MixPacket, fragment_id: FragmentIdentifier) -> Self {
+    pub(crate) fn new(
+        mix_packet: MixPacket,
+        fragment_id: FragmentIdentifier,
+        max_age: Duration,
+    ) -> Self {
         RealMessage {
             mix_packet,
             fragment_id,
+            deadline: Instant::now() + max_age,
         }
     }
+
+    fn is_expired(&self) -> bool {
+        is_past_deadline(self.deadline)
+    }
+}
+
+/// Whether `deadline` already lies in the past - the eviction check shared by every spot
+/// `poll_next` decides whether to drop a stale fragment instead of sending it.
+fn is_past_deadline(deadline: Instant) -> bool {
+    Instant::now() >= deadline
 }
 
 // messages are already prepared, etc. the real point of it is to forward it to mix_traffic
 // after sufficient delay
-pub(crate) type BatchRealMessageSender = mpsc::UnboundedSender<Vec<RealMessage>>;
-type BatchRealMessageReceiver = mpsc::UnboundedReceiver<Vec<RealMessage>>;
+//
+// the channel is bounded so that a burst of fragments (e.g. from chunking a large file)
+// cannot grow `received_buffer` without limit - producers are expected to await capacity
+// rather than enqueue unboundedly.
+pub(crate) type BatchRealMessageSender = mpsc::Sender<Vec<RealMessage>>;
+type BatchRealMessageReceiver = mpsc::Receiver<Vec<RealMessage>>;
+
+/// Creates the bounded channel pair used to feed real messages into the [`OutQueueControl`].
+pub(crate) fn new_real_message_channel(
+    capacity: usize,
+) -> (BatchRealMessageSender, BatchRealMessageReceiver) {
+    mpsc::channel(capacity)
+}
 
 pub(crate) enum StreamMessage {
     Cover,
@@ -126,45 +292,118 @@ where
 {
     type Item = StreamMessage;
 
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        // it is not yet time to return a message
-        if Pin::new(&mut self.next_delay).poll(cx).is_pending() {
-            return Poll::Pending;
-        };
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // `Self` only contains `Unpin` fields, so it's fine to drop down to a plain
+        // `&mut Self` and work with ordinary (disjoint) field borrows from here on
+        let this = self.get_mut();
 
-        // we know it's time to send a message, so let's prepare delay for the next one
-        // Get the `now` by looking at the current `delay` deadline
-        let avg_delay = self.config.average_message_sending_delay;
-        let now = self.next_delay.deadline();
-        let next_poisson_delay = sample_poisson_duration(&mut self.rng, avg_delay);
+        // a message held back by a previous topology failure takes priority over producing
+        // anything new - it keeps its slot in the schedule until it is retried
+        if let Some(pending) = this.pending_retry.as_mut() {
+            if Pin::new(&mut pending.delay).poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            // the retried message is headed for `mix_tx` too, so it also waits for capacity there
+            if Pin::new(&mut this.mix_tx).poll_ready(cx).is_pending() {
+                return Poll::Pending;
+            }
+            let PendingRetry {
+                message, backoff, ..
+            } = this.pending_retry.take().unwrap();
+            match message {
+                StreamMessage::Real(real_message) if real_message.is_expired() => {
+                    trace!(
+                        "{} went stale while waiting for a valid topology - dropping it instead",
+                        real_message.fragment_id
+                    );
+                    if this.next_delay.is_some() {
+                        // the slot is still "used up" for this tick so the sending cadence is
+                        // preserved, we just have nothing real left to put into it
+                        return Poll::Ready(Some(StreamMessage::Cover));
+                    }
+                    // VPN mode never emits cover traffic - fall through and try to produce a
+                    // fresh message below instead
+                }
+                message => {
+                    // carry the backoff forward so a repeated failure on this same message
+                    // doubles it again instead of restarting from the base delay
+                    this.pending_retry_backoff = Some(backoff);
+                    return Poll::Ready(Some(message));
+                }
+            }
+        }
 
-        // The next interval value is `next_poisson_delay` after the one that just
-        // yielded.
-        let next = now + next_poisson_delay;
-        self.next_delay.reset(next);
+        // in VPN mode (`next_delay` is `None`) there is no schedule to honour - real messages
+        // are yielded as soon as they are available and no cover traffic is ever synthesized
+        if let Some(next_delay) = this.next_delay.as_mut() {
+            // it is not yet time to return a message
+            if Pin::new(next_delay).poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
 
-        // check if we have anything immediately available
-        if let Some(real_available) = self.received_buffer.pop_front() {
-            return Poll::Ready(Some(StreamMessage::Real(real_available)));
+        // the downstream channel towards the mix traffic controller is currently full -
+        // stay pending rather than pulling (and potentially discarding) another message
+        if Pin::new(&mut this.mix_tx).poll_ready(cx).is_pending() {
+            return Poll::Pending;
         }
 
-        // decide what kind of message to send
-        match Pin::new(&mut self.real_receiver).poll_next(cx) {
-            // in the case our real message channel stream was closed, we should also indicate we are closed
-            // (and whoever is using the stream should panic)
-            Poll::Ready(None) => Poll::Ready(None),
-
-            // if there are more messages available, return first one and store the rest
-            Poll::Ready(Some(real_messages)) => {
-                self.received_buffer = real_messages.into();
-                // we MUST HAVE received at least ONE message
-                Poll::Ready(Some(StreamMessage::Real(
-                    self.received_buffer.pop_front().unwrap(),
-                )))
+        if let Some(next_delay) = this.next_delay.as_mut() {
+            // we know it's time to send a message, so let's prepare delay for the next one
+            // Get the `now` by looking at the current `delay` deadline
+            let now = next_delay.deadline();
+            let next = now + this.config.delay_distribution.sample(&mut this.rng);
+            next_delay.reset(next);
+        }
+
+        loop {
+            // check if we have anything immediately available
+            if let Some(real_available) = this.received_buffer.pop_front() {
+                if real_available.is_expired() {
+                    trace!(
+                        "{} went stale waiting to be sent - dropping it instead",
+                        real_available.fragment_id
+                    );
+                    if this.next_delay.is_some() {
+                        // the slot is still "used up" for this tick so the sending cadence is
+                        // preserved, we just have nothing real left to put into it
+                        return Poll::Ready(Some(StreamMessage::Cover));
+                    }
+                    // VPN mode has no cadence to preserve and never emits cover traffic -
+                    // keep draining the buffer for the next fragment
+                    continue;
+                }
+                return Poll::Ready(Some(StreamMessage::Real(real_available)));
             }
 
-            // otherwise construct a dummy one
-            Poll::Pending => Poll::Ready(Some(StreamMessage::Cover)),
+            // decide what kind of message to send
+            match Pin::new(&mut this.real_receiver).poll_next(cx) {
+                // in the case our real message channel stream was closed, we should also indicate we are closed
+                Poll::Ready(None) => return Poll::Ready(None),
+
+                // if there are more messages available, store them and loop around to drain them
+                Poll::Ready(Some(real_messages)) => {
+                    let mut received_buffer: VecDeque<_> = real_messages.into();
+                    let before = received_buffer.len();
+                    received_buffer.retain(|real_message| !real_message.is_expired());
+                    if received_buffer.len() != before {
+                        trace!(
+                            "dropped {} stale fragment(s) that exceeded their maximum age",
+                            before - received_buffer.len()
+                        );
+                    }
+                    this.received_buffer = received_buffer;
+                }
+
+                // otherwise construct a dummy one; VPN mode simply has nothing to send yet
+                Poll::Pending => {
+                    return if this.next_delay.is_some() {
+                        Poll::Ready(Some(StreamMessage::Cover))
+                    } else {
+                        Poll::Pending
+                    };
+                }
+            }
         }
     }
 }
@@ -182,18 +421,27 @@ where
         rng: R,
         our_full_destination: Recipient,
         topology_access: TopologyAccessor,
+        vpn_mode: bool,
     ) -> Self {
         OutQueueControl {
             config,
             ack_key,
             sent_notifier,
-            next_delay: time::delay_for(Default::default()),
+            // the real deadline only gets set once the stream actually starts running; VPN
+            // mode has no schedule to speak of at all
+            next_delay: if vpn_mode {
+                None
+            } else {
+                Some(time::delay_for(Default::default()))
+            },
             mix_tx,
             real_receiver,
             our_full_destination,
             rng,
             topology_access,
             received_buffer: VecDeque::with_capacity(0), // we won't be putting any data into this guy directly
+            pending_retry: None,
+            pending_retry_backoff: None,
         }
     }
 
@@ -205,49 +453,79 @@ where
         self.sent_notifier.unbounded_send(frag_id).unwrap();
     }
 
+    /// Holds onto `message` to be retried later, backing off exponentially (up to a cap) every
+    /// time it fails again in a row - `prior_backoff` is the backoff that produced `message` in
+    /// the first place (`None` if this is its first failure). Cover messages naturally get
+    /// coalesced this way, since no new one is produced while one is already waiting to be retried.
+    fn schedule_topology_retry(&mut self, message: StreamMessage, prior_backoff: Option<Duration>) {
+        let backoff = next_topology_backoff(
+            prior_backoff,
+            self.config.topology_backoff_base,
+            self.config.topology_backoff_cap,
+        );
+
+        warn!(
+            "No valid topology detected - will retry this message in {:?}",
+            backoff
+        );
+
+        self.pending_retry = Some(PendingRetry {
+            message,
+            delay: time::delay_for(backoff),
+            backoff,
+        });
+    }
+
     async fn on_message(&mut self, next_message: StreamMessage) {
         trace!("created new message");
 
-        let next_message = match next_message {
-            StreamMessage::Cover => {
-                // TODO for way down the line: in very rare cases (during topology update) we might have
-                // to wait a really tiny bit before actually obtaining the permit hence messing with our
-                // poisson delay, but is it really a problem?
-                let topology_permit = self.topology_access.get_read_permit().await;
-                // the ack is sent back to ourselves (and then ignored)
-                let topology_ref_option = topology_permit.try_get_valid_topology_ref(
-                    &self.our_full_destination,
-                    Some(&self.our_full_destination),
-                );
-                if topology_ref_option.is_none() {
-                    warn!(
-                        "No valid topology detected - won't send any loop cover message this time"
-                    );
-                    return;
-                }
-                let topology_ref = topology_ref_option.unwrap();
-
-                generate_loop_cover_packet(
-                    &mut self.rng,
-                    topology_ref,
-                    &*self.ack_key,
-                    &self.our_full_destination,
-                    self.config.average_ack_delay,
-                    self.config.average_packet_delay,
-                )
-                .expect("Somehow failed to generate a loop cover message with a valid topology")
+        // if `next_message` is being retried after a previous topology gap, this carries
+        // forward the backoff that produced it so a repeated failure doubles it again
+        // instead of restarting from the base delay
+        let prior_backoff = self.pending_retry_backoff.take();
+
+        // TODO for way down the line: in very rare cases (during topology update) we might have
+        // to wait a really tiny bit before actually obtaining the permit hence messing with our
+        // poisson delay, but is it really a problem?
+        let topology_permit = self.topology_access.get_read_permit().await;
+        // the ack is sent back to ourselves (and then ignored)
+        let topology_ref_option = topology_permit.try_get_valid_topology_ref(
+            &self.our_full_destination,
+            Some(&self.our_full_destination),
+        );
+
+        let topology_ref = match topology_ref_option {
+            Some(topology_ref) => topology_ref,
+            // don't drop the message on a transient topology gap - hold onto it and retry later
+            None => {
+                self.schedule_topology_retry(next_message, prior_backoff);
+                return;
             }
+        };
+
+        let next_message = match next_message {
+            StreamMessage::Cover => generate_loop_cover_packet(
+                &mut self.rng,
+                topology_ref,
+                &*self.ack_key,
+                &self.our_full_destination,
+                self.config.average_ack_delay,
+                self.config.average_packet_delay,
+            )
+            .expect("Somehow failed to generate a loop cover message with a valid topology"),
             StreamMessage::Real(real_message) => {
                 self.sent_notify(real_message.fragment_id);
                 real_message.mix_packet
             }
         };
 
-        // if this one fails, there's no retrying because it means that either:
-        // - we run out of memory
-        // - the receiver channel is closed
-        // in either case there's no recovery and we can only panic
-        self.mix_tx.unbounded_send(vec![next_message]).unwrap();
+        // `poll_next` only ever yields once `mix_tx` has reported spare capacity, so this
+        // should resolve immediately; the only way it can fail now is if the receiving end
+        // has been shut down, which is not something we can recover from here.
+        if self.mix_tx.send(vec![next_message]).await.is_err() {
+            error!("Failed to send a message to the mix traffic controller - the channel is closed");
+            return;
+        }
 
         // JS: Not entirely sure why or how it fixes stuff, but without the yield call,
         // the UnboundedReceiver [of mix_rx] will not get a chance to read anything
@@ -257,42 +535,107 @@ where
         tokio::task::yield_now().await;
     }
 
-    async fn on_batch_received(&mut self, real_messages: Vec<RealMessage>) {
-        let mut mix_packets = Vec::with_capacity(real_messages.len());
-        for real_message in real_messages.into_iter() {
-            self.sent_notify(real_message.fragment_id);
-            mix_packets.push(real_message.mix_packet);
+    // Drives the stream: in normal mode this sends messages at a certain rate and injects cover
+    // traffic whenever no real traffic is available; in VPN mode (`next_delay` is `None`) every
+    // real message is instead sent as soon as it is available and no cover traffic is injected.
+    // Both modes go through the exact same loop - `Stream::poll_next` is what tells them apart.
+    pub(crate) async fn run_out_queue_control(&mut self) {
+        if self.next_delay.is_some() {
+            debug!("Starting out queue controller...");
+            // we should set the initial delay only once we actually start the stream
+            let initial_delay = self.config.delay_distribution.sample(&mut self.rng);
+            self.next_delay = Some(time::delay_for(initial_delay));
+        } else {
+            debug!("Starting out queue controller in vpn mode...");
         }
-        self.mix_tx.unbounded_send(mix_packets).unwrap();
-    }
-
-    // Send messages at certain rate and if no real traffic is available, send cover message.
-    async fn run_normal_out_queue(&mut self) {
-        // we should set initial delay only when we actually start the stream
-        self.next_delay = time::delay_for(sample_poisson_duration(
-            &mut self.rng,
-            self.config.average_message_sending_delay,
-        ));
 
         while let Some(next_message) = self.next().await {
             self.on_message(next_message).await;
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mix_traffic::new_mix_message_channel;
+
+    // every TTL-eviction branch in `poll_next` (and `RealMessage::is_expired`) ultimately comes
+    // down to this one comparison; `MixPacket`/`FragmentIdentifier` carry no bearing on it, so
+    // it's exercised directly against `Instant`s rather than through a full `RealMessage`.
+    #[test]
+    fn a_message_is_expired_exactly_once_its_deadline_has_passed() {
+        let past_deadline = Instant::now() - Duration::from_millis(1);
+        assert!(is_past_deadline(past_deadline));
+
+        let future_deadline = Instant::now() + Duration::from_secs(60);
+        assert!(!is_past_deadline(future_deadline));
+    }
 
-    // Send real message as soon as it's available and don't inject ANY cover traffic.
-    async fn run_vpn_out_queue(&mut self) {
-        while let Some(next_messages) = self.real_receiver.next().await {
-            self.on_batch_received(next_messages).await
+    // `poll_next` treats a full `mix_tx` as a reason to stay `Pending` rather than pulling (and
+    // potentially discarding) another message - this exercises the exact same `Sink` behaviour
+    // against a real bounded channel rather than an unbounded one that can never report full.
+    #[test]
+    fn mix_tx_reports_pending_instead_of_accepting_more_once_its_capacity_is_exhausted() {
+        // a bounded mpsc channel always reserves one extra slot per sender on top of its
+        // stated capacity, so a `new_mix_message_channel(0)` with a single sender still holds
+        // exactly one in-flight batch before it reports itself full
+        let (mut mix_tx, mut mix_rx) = new_mix_message_channel(0);
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        // the single slot is free - room for one batch
+        assert!(Pin::new(&mut mix_tx).poll_ready(&mut cx).is_ready());
+        Pin::new(&mut mix_tx).start_send(Vec::new()).unwrap();
+
+        // the channel is now full - ready() must report Pending, not silently drop the next batch
+        assert!(Pin::new(&mut mix_tx).poll_ready(&mut cx).is_pending());
+
+        // once the slot is drained, capacity frees back up
+        mix_rx.try_next().unwrap();
+        assert!(Pin::new(&mut mix_tx).poll_ready(&mut cx).is_ready());
+    }
+
+    #[test]
+    fn topology_backoff_doubles_up_to_the_cap() {
+        let base = Duration::from_millis(100);
+        let cap = Duration::from_secs(1);
+
+        let first = next_topology_backoff(None, base, cap);
+        assert_eq!(first, base);
+
+        let second = next_topology_backoff(Some(first), base, cap);
+        assert_eq!(second, base * 2);
+
+        let third = next_topology_backoff(Some(second), base, cap);
+        assert_eq!(third, base * 4);
+
+        // once it reaches the cap it stays there instead of continuing to grow
+        let capped = next_topology_backoff(Some(cap), base, cap);
+        assert_eq!(capped, cap);
+    }
+
+    #[test]
+    fn uniform_delay_samples_stay_within_the_closed_range() {
+        let mut rng = rand::thread_rng();
+        let min_delay = Duration::from_millis(10);
+        let max_delay = Duration::from_millis(20);
+        let mut distribution = UniformDelay::new(min_delay, max_delay);
+
+        for _ in 0..1000 {
+            let sample = distribution.sample(&mut rng);
+            assert!(sample >= min_delay && sample <= max_delay);
         }
     }
 
-    pub(crate) async fn run_out_queue_control(&mut self, vpn_mode: bool) {
-        if vpn_mode {
-            debug!("Starting out queue controller in vpn mode...");
-            self.run_vpn_out_queue().await
-        } else {
-            debug!("Starting out queue controller...");
-            self.run_normal_out_queue().await
+    #[test]
+    fn constant_delay_always_returns_the_configured_value() {
+        let mut rng = rand::thread_rng();
+        let delay = Duration::from_millis(42);
+        let mut distribution = ConstantDelay::new(delay);
+
+        for _ in 0..10 {
+            assert_eq!(distribution.sample(&mut rng), delay);
         }
     }
 }